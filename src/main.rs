@@ -1,20 +1,26 @@
+mod notifier;
+mod runnable;
+mod shell;
+mod signals;
+mod watch;
+
 use std::{
     collections::HashMap,
-    env, fs,
-    io::{prelude::*, BufReader},
-    process,
-    sync::mpsc,
+    env, fmt, fs,
+    sync::{mpsc, Arc, Mutex},
     thread,
     time::Duration,
 };
 
-use anyhow::{anyhow, bail, ensure, Result};
+use anyhow::{bail, ensure, Context, Result};
 use clap::{command, Parser, Subcommand};
-use nix::{sys::signal, unistd};
-use signal_hook::{
-    consts::{SIGINT, SIGTERM},
-    iterator::Signals,
-};
+use regex::Regex;
+
+use notifier::NotifyOn;
+use runnable::{Runnable, RunOutcome, StdioMode};
+use shell::Shell;
+use signals::{install_signal_handlers, ChildTarget};
+use watch::OnBusy;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None, bin_name = "konk")]
@@ -63,6 +69,14 @@ enum Command {
         )]
         continue_on_failure: bool,
 
+        #[arg(
+            long,
+            help = "Debounce window (in milliseconds) for coalescing filesystem events before restarting",
+            default_value = "200",
+            global = true
+        )]
+        debounce: u64,
+
         #[arg(
             long,
             help = "Time (in seconds) for commands to exit after receiving a SIGINT/SIGTERM before a SIGKILL is sent to them",
@@ -82,9 +96,32 @@ enum Command {
         #[arg(long, help = "Do not attach label to output", global = true)]
         no_label: bool,
 
+        #[arg(
+            long,
+            help = "Do not put spawned commands in their own process group (always the case with --stdio=inherit, to preserve job control)",
+            global = true
+        )]
+        no_process_group: bool,
+
         #[arg(long, help = "Do not run commands with a subshell", global = true)]
         no_subshell: bool,
 
+        #[arg(
+            long,
+            help = "Send a desktop notification when each command finishes",
+            global = true
+        )]
+        notify: bool,
+
+        #[arg(
+            long,
+            value_enum,
+            help = "Which command completions trigger --notify",
+            default_value = "always",
+            global = true
+        )]
+        notify_on: NotifyOn,
+
         #[arg(
             short = 'n',
             long,
@@ -93,12 +130,44 @@ enum Command {
         )]
         npm: Vec<String>,
 
+        #[arg(
+            long,
+            value_enum,
+            help = "Policy for a change event arriving while commands are still running",
+            default_value = "restart",
+            global = true
+        )]
+        on_busy: OnBusy,
+
+        #[arg(
+            long,
+            help = "Shell program used to run commands (default: /bin/sh; honors $KONK_SHELL/$SHELL). Process supervision is unix-only, so this is shell selection, not Windows support",
+            global = true
+        )]
+        shell: Option<String>,
+
         #[arg(long, help = "Include command PID in output", global = true)]
         show_pid: bool,
+
+        #[arg(
+            long,
+            value_enum,
+            help = "How to wire up each command's stdin/stdout/stderr",
+            default_value = "piped",
+            global = true
+        )]
+        stdio: StdioMode,
+
+        #[arg(
+            long = "watch",
+            help = "Watch a path for changes and re-run commands on change (repeatable)",
+            global = true
+        )]
+        watch: Vec<String>,
     },
 }
 
-#[derive(Subcommand)]
+#[derive(Clone, Subcommand)]
 enum RunCommand {
     #[command(alias = "s", about = "Run commands serially (alias: s)")]
     Serially {},
@@ -108,6 +177,33 @@ enum RunCommand {
         #[arg(short = 'g', long, help = "Aggregate command output")]
         aggregate_output: bool,
     },
+
+    #[command(about = "Run commands and assert on their output and exit code")]
+    Expect {
+        #[arg(
+            long = "expect-stdout",
+            help = "Regex each command's stdout must match (aligned to commands)"
+        )]
+        expect_stdout: Vec<String>,
+
+        #[arg(
+            long = "expect-stderr",
+            help = "Regex each command's stderr must match (aligned to commands)"
+        )]
+        expect_stderr: Vec<String>,
+
+        #[arg(
+            long = "expect-exit-code",
+            help = "Expected exit code for each command (aligned to commands; default: 0)"
+        )]
+        expect_exit_code: Vec<i32>,
+
+        #[arg(
+            long,
+            help = "Path to a JSON manifest of per-command expectations (overrides --expect-* flags)"
+        )]
+        manifest: Option<String>,
+    },
 }
 
 fn main() -> Result<()> {
@@ -121,19 +217,48 @@ fn main() -> Result<()> {
             color,
             command_as_label,
             continue_on_failure,
+            debounce,
             kill_timeout,
             labels: provided_labels,
             no_label,
+            no_process_group,
             no_subshell,
+            notify,
+            notify_on,
             npm,
+            on_busy,
+            shell,
             show_pid,
+            stdio,
+            watch,
         } => {
+            let notify = notify.then_some(notify_on);
+
+            let shell = shell.map_or_else(Shell::detect, |program| Shell::from_program(&program));
+
             if let Err(err) =
                 collect_npm_commands(&mut commands, &npm, if bun { "bun" } else { "npm" })
             {
                 bail!("collecting npm commands: {}", err);
             }
 
+            ensure!(
+                stdio != StdioMode::Inherit
+                    || matches!(command, RunCommand::Serially {} | RunCommand::Expect { .. })
+                    || commands.len() <= 1,
+                "Cannot use --stdio=inherit with more than one concurrent command"
+            );
+
+            ensure!(
+                stdio == StdioMode::Piped || !matches!(command, RunCommand::Expect { .. }),
+                "run expect requires --stdio=piped (the default), since it asserts on captured output"
+            );
+
+            ensure!(
+                watch.is_empty() || !matches!(command, RunCommand::Expect { .. }),
+                "Cannot use --watch with run expect"
+            );
+
             ensure!(
                 !(no_label && command_as_label),
                 "Cannot use both --no-label and --command-as-label"
@@ -170,44 +295,138 @@ fn main() -> Result<()> {
                 )
             };
 
-            let runnables = commands
-                .into_iter()
-                .zip(labels)
-                .map(|(command, label)| Runnable {
-                    label,
-                    command,
-                    with_pid: show_pid,
-                })
-                .collect();
-
-            match command {
-                RunCommand::Serially {} => {
-                    run_serially(
-                        runnables,
-                        SeriallyOpts {
-                            continue_on_failure,
-                            kill_timeout,
-                            no_subshell,
-                        },
-                    )?;
+            if watch.is_empty() {
+                let runnables = build_runnables(
+                    &commands,
+                    &labels,
+                    BuildRunnablesOpts {
+                        show_pid,
+                        no_subshell,
+                        no_process_group,
+                        shell: shell.clone(),
+                        stdio,
+                        notify,
+                    },
+                );
+
+                match command {
+                    RunCommand::Serially {} => {
+                        run_serially(
+                            runnables,
+                            SeriallyOpts {
+                                continue_on_failure,
+                                kill_timeout,
+                                pid_sink: None,
+                            },
+                        )?;
+                    }
+                    RunCommand::Concurrently { aggregate_output } => {
+                        run_concurrently(
+                            runnables,
+                            ConcurrentlyOpts {
+                                aggregate_output,
+                                continue_on_failure,
+                                kill_timeout,
+                                pid_sink: None,
+                            },
+                        )?;
+                    }
+                    RunCommand::Expect {
+                        expect_stdout,
+                        expect_stderr,
+                        expect_exit_code,
+                        manifest,
+                    } => {
+                        let expectations = build_expectations(
+                            &commands,
+                            &expect_stdout,
+                            &expect_stderr,
+                            &expect_exit_code,
+                            manifest.as_deref(),
+                        )?;
+
+                        run_expect(runnables, expectations, kill_timeout)?;
+                    }
                 }
-                RunCommand::Concurrently { aggregate_output } => {
-                    run_concurrently(
-                        runnables,
-                        ConcurrentlyOpts {
-                            aggregate_output,
-                            continue_on_failure,
-                            kill_timeout,
+            } else {
+                let watch_opts = watch::WatchOpts {
+                    paths: watch,
+                    debounce: Duration::from_millis(debounce),
+                    on_busy,
+                    kill_timeout,
+                };
+
+                watch::run(watch_opts, move |live_pids| {
+                    let runnables = build_runnables(
+                        &commands,
+                        &labels,
+                        BuildRunnablesOpts {
+                            show_pid,
                             no_subshell,
+                            no_process_group,
+                            shell: shell.clone(),
+                            stdio,
+                            notify,
                         },
-                    )?;
-                }
+                    );
+
+                    match command.clone() {
+                        RunCommand::Serially {} => run_serially(
+                            runnables,
+                            SeriallyOpts {
+                                continue_on_failure,
+                                kill_timeout,
+                                pid_sink: Some(live_pids),
+                            },
+                        ),
+                        RunCommand::Concurrently { aggregate_output } => run_concurrently(
+                            runnables,
+                            ConcurrentlyOpts {
+                                aggregate_output,
+                                continue_on_failure,
+                                kill_timeout,
+                                pid_sink: Some(live_pids),
+                            },
+                        ),
+                        RunCommand::Expect { .. } => {
+                            unreachable!("--watch with run expect is rejected above")
+                        }
+                    }
+                })?;
             }
         }
     }
     Ok(())
 }
 
+struct BuildRunnablesOpts {
+    show_pid: bool,
+    no_subshell: bool,
+    no_process_group: bool,
+    shell: Shell,
+    stdio: StdioMode,
+    notify: Option<NotifyOn>,
+}
+
+fn build_runnables(commands: &[String], labels: &[String], opts: BuildRunnablesOpts) -> Vec<Runnable> {
+    commands
+        .iter()
+        .cloned()
+        .zip(labels.iter().cloned())
+        .map(|(command, label)| Runnable {
+            label,
+            show_pid: opts.show_pid,
+            command,
+            working_dir: None,
+            use_subshell: !opts.no_subshell,
+            shell: opts.shell.clone(),
+            stdio: opts.stdio,
+            use_process_group: !opts.no_process_group,
+            notify: opts.notify,
+        })
+        .collect()
+}
+
 struct LabelOpts {
     command_as_label: bool,
     color: bool,
@@ -280,44 +499,33 @@ fn collect_npm_commands(commands: &mut Vec<String>, npm: &[String], run_with: &s
     Ok(())
 }
 
-struct Runnable {
-    label: String,
-    with_pid: bool,
-    command: String,
-}
-
 struct SeriallyOpts {
     continue_on_failure: bool,
     kill_timeout: u16,
-    no_subshell: bool,
+
+    /// When running under `--watch`, the shared set of live targets to
+    /// populate instead of installing this run's own signal handlers
+    pid_sink: Option<Arc<Mutex<Vec<ChildTarget>>>>,
 }
 
 fn run_serially(runnables: Vec<Runnable>, opts: SeriallyOpts) -> Result<()> {
     let mut command_failed = false;
 
-    for runnable in runnables {
-        let (pid, handle) = start_command(
-            runnable,
-            CommandOpts {
-                aggregate_output: false,
-                no_subshell: opts.no_subshell,
-            },
-        )?;
-
-        install_signal_handlers(vec![pid], opts.kill_timeout)?;
+    for mut runnable in runnables {
+        let handle = runnable.run(false)?;
+        let target = ChildTarget::from(&handle);
 
-        let exit_status = handle
-            .join()
-            .map_err(|e| anyhow!("thread panicked: {:?}", e))??;
-
-        if exit_status.success() {
-            continue;
+        match &opts.pid_sink {
+            Some(sink) => sink.lock().unwrap().push(target),
+            None => install_signal_handlers(Arc::new(Mutex::new(vec![target])), opts.kill_timeout)?,
         }
 
-        command_failed = true;
+        if handle.wait().is_err() {
+            command_failed = true;
 
-        if !opts.continue_on_failure {
-            break;
+            if !opts.continue_on_failure {
+                break;
+            }
         }
     }
 
@@ -332,57 +540,42 @@ struct ConcurrentlyOpts {
     aggregate_output: bool,
     continue_on_failure: bool,
     kill_timeout: u16,
-    no_subshell: bool,
+
+    /// When running under `--watch`, the shared set of live targets to
+    /// populate instead of installing this run's own signal handlers
+    pid_sink: Option<Arc<Mutex<Vec<ChildTarget>>>>,
 }
 
 fn run_concurrently(runnables: Vec<Runnable>, opts: ConcurrentlyOpts) -> Result<()> {
-    let (tx, rx) = mpsc::channel::<Result<process::ExitStatus>>();
-    let mut pids: Vec<u32> = Vec::new();
-
-    for runnable in runnables {
-        let (pid, handle) = start_command(
-            runnable,
-            CommandOpts {
-                aggregate_output: opts.aggregate_output,
-                no_subshell: opts.no_subshell,
-            },
-        )?;
+    let (tx, rx) = mpsc::channel::<Result<()>>();
+    let mut targets: Vec<ChildTarget> = Vec::new();
 
-        pids.push(pid);
+    for mut runnable in runnables {
+        let handle = runnable.run(opts.aggregate_output)?;
+        targets.push(ChildTarget::from(&handle));
 
         let tx = tx.clone();
         thread::spawn(move || {
-            match handle
-                .join()
-                .map_err(|e| anyhow!("thread panicked: {:?}", e))
-            {
-                Ok(r) => tx.send(r).unwrap(),
-                Err(e) => tx.send(Err(e)).unwrap(),
-            };
+            tx.send(handle.wait()).unwrap();
         });
     }
 
-    install_signal_handlers(pids, opts.kill_timeout)?;
+    match &opts.pid_sink {
+        Some(sink) => sink.lock().unwrap().extend(targets),
+        None => install_signal_handlers(Arc::new(Mutex::new(targets)), opts.kill_timeout)?,
+    }
 
     drop(tx);
 
     let mut command_failed = false;
 
     for result in rx {
-        match result {
-            Ok(exit_status) => {
-                if exit_status.success() {
-                    continue;
-                }
-
-                command_failed = true;
+        if result.is_err() {
+            command_failed = true;
 
-                if !opts.continue_on_failure {
-                    break;
-                }
+            if !opts.continue_on_failure {
+                break;
             }
-
-            Err(e) => return Err(e),
         }
     }
 
@@ -393,145 +586,342 @@ fn run_concurrently(runnables: Vec<Runnable>, opts: ConcurrentlyOpts) -> Result<
     Ok(())
 }
 
-struct CommandOpts {
-    aggregate_output: bool,
-    no_subshell: bool,
+/// A compiled assertion against one command's exit code and, optionally, its
+/// stdout and/or stderr.
+#[derive(Debug)]
+struct Expectation {
+    stdout: Option<Regex>,
+    stderr: Option<Regex>,
+    exit_code: i32,
 }
 
-fn start_command(
-    runnable: Runnable,
-    opts: CommandOpts,
-) -> Result<(u32, thread::JoinHandle<Result<process::ExitStatus>>)> {
-    let mut cmd;
-    if opts.no_subshell {
-        let parts = shell_words::split(&runnable.command)?;
-        let (command, args) = parts.split_first().ok_or_else(|| anyhow!("no command"))?;
-        cmd = process::Command::new(command);
-        cmd.args(args);
+impl Expectation {
+    fn compile(stdout: Option<String>, stderr: Option<String>, exit_code: i32) -> Result<Expectation> {
+        Ok(Expectation {
+            stdout: stdout
+                .map(|pattern| Regex::new(&pattern))
+                .transpose()
+                .context("compile --expect-stdout regex")?,
+            stderr: stderr
+                .map(|pattern| Regex::new(&pattern))
+                .transpose()
+                .context("compile --expect-stderr regex")?,
+            exit_code,
+        })
+    }
+}
+
+/// An entry in a `--manifest` file, one per command in order.
+#[derive(serde::Deserialize)]
+struct ManifestEntry {
+    stdout: Option<String>,
+    stderr: Option<String>,
+    exit_code: Option<i32>,
+}
+
+fn build_expectations(
+    commands: &[String],
+    expect_stdout: &[String],
+    expect_stderr: &[String],
+    expect_exit_code: &[i32],
+    manifest: Option<&str>,
+) -> Result<Vec<Expectation>> {
+    if let Some(path) = manifest {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("read manifest: {}", path))?;
+        let entries: Vec<ManifestEntry> =
+            serde_json::from_str(&raw).context("parse manifest")?;
+
+        ensure!(
+            entries.len() == commands.len(),
+            "Number of manifest entries must match number of commands"
+        );
+
+        entries
+            .into_iter()
+            .map(|entry| Expectation::compile(entry.stdout, entry.stderr, entry.exit_code.unwrap_or(0)))
+            .collect()
     } else {
-        cmd = process::Command::new("/bin/sh");
-        cmd.args(["-c", &runnable.command]);
+        ensure!(
+            expect_stdout.len() == 0 || expect_stdout.len() == commands.len(),
+            "Number of --expect-stdout regexes must match number of commands"
+        );
+
+        ensure!(
+            expect_stderr.len() == 0 || expect_stderr.len() == commands.len(),
+            "Number of --expect-stderr regexes must match number of commands"
+        );
+
+        ensure!(
+            expect_exit_code.len() == 0 || expect_exit_code.len() == commands.len(),
+            "Number of --expect-exit-code values must match number of commands"
+        );
+
+        (0..commands.len())
+            .map(|i| {
+                Expectation::compile(
+                    expect_stdout.get(i).cloned(),
+                    expect_stderr.get(i).cloned(),
+                    expect_exit_code.get(i).copied().unwrap_or(0),
+                )
+            })
+            .collect()
     }
+}
 
-    let mut child = cmd
-        .stdout(process::Stdio::piped())
-        .stderr(process::Stdio::piped())
-        .spawn()?;
+/// The result of checking a finished command's captured output and exit
+/// status against its [Expectation].
+struct CommandOutcome {
+    label: String,
+    passed: bool,
+    failures: Vec<String>,
+    stdout: Vec<String>,
+    stderr: Vec<String>,
+}
 
-    let (tx, rx) = mpsc::channel::<String>();
+impl fmt::Display for CommandOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.passed {
+            return write!(f, "{}PASS", self.label);
+        }
 
-    let stdout = child.stdout.take().ok_or_else(|| anyhow!("no stdout"))?;
-    let stdout_handle = read_stream(stdout, tx.clone());
+        write!(f, "{}FAIL ({})", self.label, self.failures.join("; "))?;
 
-    let stderr = child.stderr.take().ok_or_else(|| anyhow!("no stderr"))?;
-    let stderr_handle = read_stream(stderr, tx.clone());
+        for line in &self.stdout {
+            write!(f, "\n{}stdout: {}", self.label, line)?;
+        }
 
-    let pid = child.id();
+        for line in &self.stderr {
+            write!(f, "\n{}stderr: {}", self.label, line)?;
+        }
 
-    Ok((
-        pid,
-        thread::spawn(move || -> Result<process::ExitStatus> {
-            drop(tx);
+        Ok(())
+    }
+}
 
-            let mut lines = Vec::<String>::new();
+fn check_expectation(outcome: RunOutcome, expectation: &Expectation) -> CommandOutcome {
+    let mut failures = Vec::new();
+
+    if outcome.status.code() != Some(expectation.exit_code) {
+        failures.push(format!(
+            "expected exit code {}, got {}",
+            expectation.exit_code,
+            outcome
+                .status
+                .code()
+                .map_or("none (terminated by signal)".to_string(), |code| code.to_string())
+        ));
+    }
 
-            let label = if runnable.with_pid {
-                format!("{}(PID: {}) ", runnable.label, pid)
-            } else {
-                runnable.label
-            };
+    if let Some(re) = &expectation.stdout {
+        let joined = outcome.stdout.join("\n");
 
-            for mut line in rx {
-                line = format!("{}{}", label, line);
+        if !re.is_match(&joined) {
+            failures.push(format!("stdout did not match /{}/", re));
+        }
+    }
 
-                if opts.aggregate_output {
-                    lines.push(line);
-                } else {
-                    println!("{}", line);
-                }
-            }
+    if let Some(re) = &expectation.stderr {
+        let joined = outcome.stderr.join("\n");
 
-            stdout_handle
-                .join()
-                .map_err(|e| anyhow!("thread panicked: {:?}", e))??;
+        if !re.is_match(&joined) {
+            failures.push(format!("stderr did not match /{}/", re));
+        }
+    }
+
+    let passed = failures.is_empty();
 
-            stderr_handle
-                .join()
-                .map_err(|e| anyhow!("thread panicked: {:?}", e))??;
+    CommandOutcome {
+        label: outcome.label,
+        passed,
+        failures,
+        stdout: if passed { Vec::new() } else { outcome.stdout },
+        stderr: if passed { Vec::new() } else { outcome.stderr },
+    }
+}
 
-            let exit_status = child.wait()?;
+fn run_expect(runnables: Vec<Runnable>, expectations: Vec<Expectation>, kill_timeout: u16) -> Result<()> {
+    let mut any_failed = false;
 
-            for line in lines.iter() {
-                println!("{}", line);
-            }
+    // Install handlers once for the whole loop, tracking the currently
+    // running command in a shared cell (as watch.rs does for its live
+    // commands), instead of installing a fresh listener thread per command.
+    let live_target: Arc<Mutex<Vec<ChildTarget>>> = Arc::new(Mutex::new(Vec::new()));
+    install_signal_handlers(live_target.clone(), kill_timeout)?;
 
-            eprintln!("{}{}", label, exit_status);
+    for (mut runnable, expectation) in runnables.into_iter().zip(expectations) {
+        let handle = runnable.run(true)?;
+        *live_target.lock().unwrap() = vec![ChildTarget::from(&handle)];
 
-            Ok(exit_status)
-        }),
-    ))
-}
+        let outcome = handle.wait_for_output()?;
+        live_target.lock().unwrap().clear();
 
-fn read_stream<R>(stream: R, into: mpsc::Sender<String>) -> thread::JoinHandle<Result<()>>
-where
-    R: Read + Send + 'static,
-{
-    thread::spawn(move || -> Result<()> {
-        let reader = BufReader::new(stream);
+        let result = check_expectation(outcome, &expectation);
 
-        for line in reader.lines() {
-            into.send(line?)?;
+        println!("{result}");
+
+        if !result.passed {
+            any_failed = true;
         }
+    }
 
-        Ok(())
-    })
-}
+    ensure!(!any_failed, "One or more commands failed their expectations.");
 
-fn install_signal_handlers(pids: Vec<u32>, timeout: u16) -> Result<()> {
-    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    Ok(())
+}
 
-    thread::spawn(move || {
-        let mut received_signal = false;
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus};
 
-        for signal in signals.forever() {
-            if let SIGINT | SIGTERM = signal {
-                if received_signal {
-                    eprintln!("Received signal again. Killing processes.");
-                    kill_all_and_exit(&pids);
-                } else {
-                    received_signal = true;
+    use super::*;
 
-                    let timeout = timeout.clone();
-                    let pids = pids.clone();
+    fn exit_status(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
 
-                    thread::spawn(move || {
-                        thread::sleep(Duration::from_secs(timeout.to_owned().into()));
-                        eprintln!("Timeout. Killing child processes.");
-                        kill_all_and_exit(&pids);
-                    });
+    fn signaled_status(signal: i32) -> ExitStatus {
+        // The low 7 bits holding a nonzero signal number (with the high bit
+        // of that byte clear) is how a signal-terminated status is encoded;
+        // `ExitStatus::code()` returns `None` for it.
+        ExitStatus::from_raw(signal)
+    }
 
-                    eprintln!("Received signal. Waiting for child processes to exit.");
-                }
-            }
+    fn outcome(status: ExitStatus, stdout: &[&str], stderr: &[&str]) -> RunOutcome {
+        RunOutcome {
+            label: "[test] ".to_string(),
+            status,
+            stdout: stdout.iter().map(|s| s.to_string()).collect(),
+            stderr: stderr.iter().map(|s| s.to_string()).collect(),
         }
-    });
+    }
 
-    Ok(())
-}
+    #[test]
+    fn check_expectation_passes_when_everything_matches() {
+        let expectation = Expectation::compile(Some("^ok$".to_string()), None, 0).unwrap();
+        let result = check_expectation(outcome(exit_status(0), &["ok"], &[]), &expectation);
 
-fn kill_all_and_exit(pids: &[u32]) {
-    pids.iter().for_each(kill_process);
-    process::exit(130);
-}
+        assert!(result.passed);
+        assert!(result.failures.is_empty());
+        assert!(result.stdout.is_empty());
+    }
 
-fn kill_process(pid: &u32) {
-    // https://github.com/nix-rust/nix/issues/656#issuecomment-2056684715
-    let pid = unistd::Pid::from_raw(pid.to_owned() as i32);
+    #[test]
+    fn check_expectation_fails_on_exit_code_mismatch() {
+        let expectation = Expectation::compile(None, None, 0).unwrap();
+        let result = check_expectation(outcome(exit_status(1), &[], &[]), &expectation);
 
-    eprintln!("Sending SIGKILL to process {}.", pid);
+        assert!(!result.passed);
+        assert_eq!(result.failures, vec!["expected exit code 0, got 1"]);
+    }
+
+    #[test]
+    fn check_expectation_fails_on_signal_termination() {
+        let expectation = Expectation::compile(None, None, 0).unwrap();
+        let result = check_expectation(outcome(signaled_status(9), &[], &[]), &expectation);
+
+        assert!(!result.passed);
+        assert_eq!(
+            result.failures,
+            vec!["expected exit code 0, got none (terminated by signal)"]
+        );
+    }
+
+    #[test]
+    fn check_expectation_fails_on_stdout_mismatch() {
+        let expectation = Expectation::compile(Some("^ok$".to_string()), None, 0).unwrap();
+        let result = check_expectation(outcome(exit_status(0), &["nope"], &[]), &expectation);
 
-    match signal::kill(pid, signal::SIGKILL) {
-        Err(e) => eprintln!("Failed to send SIGKILL to process {}: {:?}", pid, e),
-        Ok(_) => {}
-    };
+        assert!(!result.passed);
+        assert_eq!(result.stdout, vec!["nope"]);
+    }
+
+    #[test]
+    fn check_expectation_fails_on_stderr_mismatch() {
+        let expectation = Expectation::compile(None, Some("^boom$".to_string()), 0).unwrap();
+        let result = check_expectation(outcome(exit_status(0), &[], &["nope"]), &expectation);
+
+        assert!(!result.passed);
+        assert_eq!(result.stderr, vec!["nope"]);
+    }
+
+    #[test]
+    fn build_expectations_from_inline_flags() {
+        let commands = vec!["a".to_string(), "b".to_string()];
+        let expectations = build_expectations(
+            &commands,
+            &["^ok$".to_string(), "^good$".to_string()],
+            &[],
+            &[0, 1],
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(expectations.len(), 2);
+        assert_eq!(expectations[0].exit_code, 0);
+        assert!(expectations[0].stdout.as_ref().unwrap().is_match("ok"));
+        assert_eq!(expectations[1].exit_code, 1);
+        assert!(expectations[1].stdout.as_ref().unwrap().is_match("good"));
+    }
+
+    #[test]
+    fn build_expectations_rejects_mismatched_inline_flag_count() {
+        let commands = vec!["a".to_string(), "b".to_string()];
+        let err = build_expectations(
+            &commands,
+            &["^ok$".to_string(), "^ok$".to_string(), "^ok$".to_string()],
+            &[],
+            &[],
+            None,
+        )
+        .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("Number of --expect-stdout regexes must match number of commands"));
+    }
+
+    #[test]
+    fn build_expectations_from_manifest_overrides_inline_flags() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("konk-test-manifest-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"[{"exit_code": 2}, {"stdout": "^ok$"}]"#,
+        )
+        .unwrap();
+
+        let commands = vec!["a".to_string(), "b".to_string()];
+        // Inline flags are the wrong length for `commands`, which would
+        // normally be rejected, but the manifest takes priority and they're
+        // never consulted.
+        let expectations =
+            build_expectations(&commands, &["one".to_string(), "two".to_string(), "three".to_string()], &[], &[], Some(path.to_str().unwrap()))
+                .unwrap();
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(expectations.len(), 2);
+        assert_eq!(expectations[0].exit_code, 2);
+        assert_eq!(expectations[1].exit_code, 0);
+        assert!(expectations[1].stdout.as_ref().unwrap().is_match("ok"));
+    }
+
+    #[test]
+    fn build_expectations_rejects_mismatched_manifest_entry_count() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("konk-test-manifest-mismatch-{}.json", std::process::id()));
+        fs::write(&path, r#"[{"exit_code": 0}]"#).unwrap();
+
+        let commands = vec!["a".to_string(), "b".to_string()];
+        let err = build_expectations(&commands, &[], &[], &[], Some(path.to_str().unwrap()))
+            .unwrap_err();
+
+        fs::remove_file(&path).unwrap();
+
+        assert!(err
+            .to_string()
+            .contains("Number of manifest entries must match number of commands"));
+    }
 }
+
@@ -0,0 +1,39 @@
+//! Desktop notifications for command completion, via the `notify-rust` crate.
+
+use notify_rust::{Notification, Urgency};
+
+/// Which command completions should trigger a desktop notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum NotifyOn {
+    /// Notify when any command finishes, whether it succeeded or failed
+    Always,
+    /// Notify only when a command fails
+    Failure,
+}
+
+/// Fires a desktop notification summarizing a finished command's label and
+/// exit status, honoring `on`'s policy for which completions warrant one.
+///
+/// Failures are shown at critical urgency so they aren't missed among other
+/// notifications.
+pub fn notify(label: &str, success: bool, on: NotifyOn) {
+    if success && on == NotifyOn::Failure {
+        return;
+    }
+
+    let mut notification = Notification::new();
+    notification.summary(if success {
+        "konk: command succeeded"
+    } else {
+        "konk: command failed"
+    });
+    notification.body(label.trim());
+
+    if !success {
+        notification.urgency(Urgency::Critical);
+    }
+
+    if let Err(err) = notification.show() {
+        eprintln!("Failed to send desktop notification: {err}");
+    }
+}
@@ -1,4 +1,6 @@
 use anyhow::{Context, Result};
+#[cfg(unix)]
+use nix::unistd;
 use std::{
     fmt,
     fs::canonicalize,
@@ -7,6 +9,23 @@ use std::{
     sync::{Arc, Mutex},
     thread::{spawn, JoinHandle},
 };
+#[cfg(unix)]
+use std::{io, os::unix::process::CommandExt};
+
+use crate::notifier::NotifyOn;
+use crate::shell::Shell;
+
+/// How a command's stdin/stdout/stderr should be wired up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StdioMode {
+    /// Pipe output back to konk, labeling (and optionally aggregating) each line
+    Piped,
+    /// Inherit konk's own stdin/stdout/stderr directly, unlabeled, for
+    /// interactive commands (TTY programs, prompts, progress bars)
+    Inherit,
+    /// Discard all output
+    Null,
+}
 
 /// A representation of a command to be run and streamed to stdout
 #[derive(Debug)]
@@ -19,15 +38,37 @@ pub struct Runnable {
 
     /// The command and its arguments to run as a single string
     ///
-    /// If [use_subshell](Runnable::use_subshell) is true, this will be run in a subshell (/bin/sh).
-    /// Otherwise, the command will be split into a command and its arguments.
+    /// If [use_subshell](Runnable::use_subshell) is true, this will be run in
+    /// a subshell using [shell](Runnable::shell). Otherwise, the command will
+    /// be split into a command and its arguments and run directly.
     pub command: String,
 
     /// The working directory to run the command in
     pub working_dir: Option<String>,
 
-    /// Whether to run the command in a subshell (/bin/sh)
+    /// Whether to run the command in a subshell (see [shell](Runnable::shell))
     pub use_subshell: bool,
+
+    /// The shell to run the command in, when [use_subshell](Runnable::use_subshell) is true
+    pub shell: Shell,
+
+    /// How the command's stdin/stdout/stderr are wired up
+    pub stdio: StdioMode,
+
+    /// Whether to put the command in its own process group, so that
+    /// signalling it (see [RunHandle::signal]) reaches any descendants it
+    /// spawns, e.g. a server started from a subshell.
+    ///
+    /// Ignored when [stdio](Runnable::stdio) is [StdioMode::Inherit]: putting
+    /// an inherited-stdio command in its own session via `setsid()` would
+    /// detach it from the terminal's foreground process group even though it
+    /// shares the terminal directly, breaking job control (Ctrl-Z, `fg`/`bg`)
+    /// for interactive programs.
+    pub use_process_group: bool,
+
+    /// When set, fires a desktop notification on completion (see
+    /// [RunHandle::wait]) according to the given policy
+    pub notify: Option<NotifyOn>,
 }
 
 impl Runnable {
@@ -35,105 +76,131 @@ impl Runnable {
     ///
     /// If `aggregate_output` is true, the output of the command will be
     /// collected and printed at the end. Otherwise, the output will be
-    /// streamed to stdout as it is produced.
+    /// streamed to stdout as it is produced. This only applies when
+    /// [stdio](Runnable::stdio) is [StdioMode::Piped]; in
+    /// [StdioMode::Inherit] or [StdioMode::Null] there is no output to
+    /// collect or label.
     ///
     /// Returns a [RunHandle] that can be used to wait for the
     /// command to finish.
     pub fn run(&mut self, aggregate_output: bool) -> Result<RunHandle> {
-        let mut child;
-
-        let working_dir;
-        if let Some(dir) = &self.working_dir {
-            working_dir = Some(canonicalize(dir).context("canonicalize working directory")?);
-        } else {
-            working_dir = None;
-        }
-
-        if self.use_subshell {
-            let mut cmd = Command::new("/bin/sh");
-            cmd.args(["-c", &self.command]);
-
-            if let Some(working_dir) = working_dir {
-                cmd.current_dir(working_dir);
-            }
-
-            child = cmd
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .context(format!("spawn process: {}", self.command))?;
+        let mut cmd = if self.use_subshell {
+            let (program, arg) = self.shell.program_and_arg();
+            let mut cmd = Command::new(program);
+            cmd.args([arg, &self.command]);
+            cmd
         } else {
             let parts = shell_words::split(&self.command).context("split command")?;
-            let command = parts.get(0).context("get command")?;
+            let command = parts.first().context("get command")?;
             let rest = parts.get(1..).context("get arguments")?;
 
             let mut cmd = Command::new(command);
             cmd.args(rest);
+            cmd
+        };
+
+        if let Some(dir) = &self.working_dir {
+            cmd.current_dir(canonicalize(dir).context("canonicalize working directory")?);
+        }
 
-            if let Some(working_dir) = working_dir {
-                cmd.current_dir(working_dir);
+        // See the doc comment on `use_process_group` for why inherited stdio
+        // opts out of process groups regardless of the flag.
+        let use_process_group = self.use_process_group && self.stdio != StdioMode::Inherit;
+
+        #[cfg(unix)]
+        if use_process_group {
+            // Safety: setsid() is async-signal-safe and only touches the
+            // child process's own state between fork and exec.
+            unsafe {
+                cmd.pre_exec(|| {
+                    unistd::setsid()
+                        .map(|_| ())
+                        .map_err(|errno| io::Error::from_raw_os_error(errno as i32))
+                });
             }
+        }
 
-            child = cmd
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .context(format!("spawn process: {command}"))?;
+        match self.stdio {
+            StdioMode::Piped => {
+                cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+            }
+            StdioMode::Inherit => {
+                cmd.stdin(Stdio::inherit())
+                    .stdout(Stdio::inherit())
+                    .stderr(Stdio::inherit());
+            }
+            StdioMode::Null => {
+                cmd.stdout(Stdio::null()).stderr(Stdio::null());
+            }
         }
 
+        let mut child = cmd
+            .spawn()
+            .context(format!("spawn process: {}", self.command))?;
+
         if self.show_pid {
             self.label = format!("{}(PID: {}) ", self.label, child.id());
         }
 
-        let stdout = child.stdout.take().context("get child stdout")?;
-        let stderr = child.stderr.take().context("get child stderr")?;
+        let stdout_lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let stderr_lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
 
-        let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let (out_handle, err_handle) = if self.stdio == StdioMode::Piped {
+            let stdout = child.stdout.take().context("get child stdout")?;
+            let stderr = child.stderr.take().context("get child stderr")?;
 
-        let out_lines = lines.clone();
-        let out_label = self.label.clone();
+            let out_lines = stdout_lines.clone();
+            let out_label = self.label.clone();
 
-        let out_handle: JoinHandle<Result<()>> = spawn(move || {
-            let reader = BufReader::new(stdout);
+            let out_handle: JoinHandle<Result<()>> = spawn(move || {
+                let reader = BufReader::new(stdout);
 
-            for line in reader.lines() {
-                let line = line.context("read line")?;
+                for line in reader.lines() {
+                    let line = line.context("read line")?;
 
-                if aggregate_output {
-                    out_lines.lock().unwrap().push(line.clone());
-                } else {
-                    println!("{out_label}{line}");
+                    if aggregate_output {
+                        out_lines.lock().unwrap().push(line.clone());
+                    } else {
+                        println!("{out_label}{line}");
+                    }
                 }
-            }
 
-            Ok(())
-        });
+                Ok(())
+            });
 
-        let err_lines = lines.clone();
-        let err_label = self.label.clone();
+            let err_lines = stderr_lines.clone();
+            let err_label = self.label.clone();
 
-        let err_handle: JoinHandle<Result<()>> = spawn(move || {
-            let reader = BufReader::new(stderr);
+            let err_handle: JoinHandle<Result<()>> = spawn(move || {
+                let reader = BufReader::new(stderr);
 
-            for line in reader.lines() {
-                let line = line.context("read line")?;
+                for line in reader.lines() {
+                    let line = line.context("read line")?;
 
-                if aggregate_output {
-                    err_lines.lock().unwrap().push(line.clone());
-                } else {
-                    println!("{err_label}{line}");
+                    if aggregate_output {
+                        err_lines.lock().unwrap().push(line.clone());
+                    } else {
+                        println!("{err_label}{line}");
+                    }
                 }
-            }
 
-            Ok(())
-        });
+                Ok(())
+            });
+
+            (Some(out_handle), Some(err_handle))
+        } else {
+            (None, None)
+        };
 
         Ok(RunHandle {
             child,
             label: self.label.clone(),
             err_handle,
             out_handle,
-            output: lines,
+            stdout_lines,
+            stderr_lines,
+            process_group: use_process_group,
+            notify: self.notify,
         })
     }
 }
@@ -144,9 +211,38 @@ impl Runnable {
 pub struct RunHandle {
     child: std::process::Child,
     label: String,
-    out_handle: JoinHandle<Result<()>>,
-    err_handle: JoinHandle<Result<()>>,
-    output: Arc<Mutex<Vec<String>>>,
+
+    /// `None` when the command ran with [StdioMode::Inherit] or
+    /// [StdioMode::Null], since there is no piped output to read
+    out_handle: Option<JoinHandle<Result<()>>>,
+    err_handle: Option<JoinHandle<Result<()>>>,
+    stdout_lines: Arc<Mutex<Vec<String>>>,
+    stderr_lines: Arc<Mutex<Vec<String>>>,
+
+    /// Whether [pid](RunHandle::pid) identifies a process group to signal
+    /// (see [signal](RunHandle::signal)) rather than a single process
+    process_group: bool,
+
+    /// When set, [wait](RunHandle::wait) fires a desktop notification on
+    /// completion according to the given policy
+    notify: Option<NotifyOn>,
+}
+
+/// The result of waiting for a [RunHandle] to finish: its exit status and,
+/// when run with output aggregation, the lines it wrote to each stream.
+#[derive(Debug)]
+pub struct RunOutcome {
+    /// The command's label, as printed alongside its output
+    pub label: String,
+
+    /// The command's exit status
+    pub status: ExitStatus,
+
+    /// Lines written to stdout, populated only when aggregating output
+    pub stdout: Vec<String>,
+
+    /// Lines written to stderr, populated only when aggregating output
+    pub stderr: Vec<String>,
 }
 
 /// An error indicating that a command exited with a non-zero status
@@ -168,6 +264,16 @@ impl fmt::Display for ExitStatusError {
 }
 
 impl RunHandle {
+    /// Returns the PID of the running (or exited) child process
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Returns whether [pid](RunHandle::pid) identifies a process group
+    pub fn process_group(&self) -> bool {
+        self.process_group
+    }
+
     /// Waits for the command to finish
     ///
     /// If the command ran with output aggregation (see [Runnable::run]), the output will be printed
@@ -175,23 +281,21 @@ impl RunHandle {
     ///
     /// Returns an error if the command exited with a non-zero status.
     pub fn wait(mut self) -> Result<()> {
-        self.out_handle
-            .join()
-            .map_err(|err| anyhow::anyhow!("join stdout: panicked: {:?}", err))
-            .context("join stdout thread")??;
-
-        self.err_handle
-            .join()
-            .map_err(|err| anyhow::anyhow!("join stderr: panicked: {:?}", err))
-            .context("join stderr thread")??;
-
-        let status = self.child.wait().context("wait for child")?;
+        let status = self.join_and_wait()?;
 
         // Will be empty if aggregate_output is false
-        for line in self.output.lock().unwrap().iter() {
+        for line in self.stdout_lines.lock().unwrap().iter() {
             println!("{}{}", self.label, line);
         }
 
+        for line in self.stderr_lines.lock().unwrap().iter() {
+            println!("{}{}", self.label, line);
+        }
+
+        if let Some(on) = self.notify {
+            crate::notifier::notify(&self.label, status.success(), on);
+        }
+
         if !status.success() {
             let label = self.label.clone();
             let err = ExitStatusError { label, status };
@@ -200,4 +304,44 @@ impl RunHandle {
 
         Ok(())
     }
+
+    /// Waits for the command to finish, returning its exit status and
+    /// captured output instead of printing it or treating a non-zero status
+    /// as an error.
+    ///
+    /// Used by `run expect`, which needs to inspect a command's output and
+    /// exit status even when it's expected to fail.
+    pub fn wait_for_output(mut self) -> Result<RunOutcome> {
+        let status = self.join_and_wait()?;
+
+        if let Some(on) = self.notify {
+            crate::notifier::notify(&self.label, status.success(), on);
+        }
+
+        Ok(RunOutcome {
+            label: self.label.clone(),
+            status,
+            stdout: self.stdout_lines.lock().unwrap().clone(),
+            stderr: self.stderr_lines.lock().unwrap().clone(),
+        })
+    }
+
+    /// Joins the reader threads (if any) and waits for the child to exit.
+    fn join_and_wait(&mut self) -> Result<ExitStatus> {
+        if let Some(out_handle) = self.out_handle.take() {
+            out_handle
+                .join()
+                .map_err(|err| anyhow::anyhow!("join stdout: panicked: {:?}", err))
+                .context("join stdout thread")??;
+        }
+
+        if let Some(err_handle) = self.err_handle.take() {
+            err_handle
+                .join()
+                .map_err(|err| anyhow::anyhow!("join stderr: panicked: {:?}", err))
+                .context("join stderr thread")??;
+        }
+
+        self.child.wait().context("wait for child")
+    }
 }
@@ -0,0 +1,64 @@
+//! Selection of the shell used to interpret a [Runnable](crate::runnable::Runnable)'s
+//! command when run in subshell mode.
+//!
+//! This only controls which interpreter a command string is handed to; it
+//! does not make konk itself portable. konk's process supervision (signal
+//! forwarding, process groups, `--watch`) is built on `nix`, which is
+//! unix-only, so the binary as a whole does not build for Windows yet even
+//! though [Shell::detect] picks [Shell::Cmd] there.
+
+use std::env;
+
+/// The interpreter (and its command-string flag) used to run a command in a
+/// subshell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shell {
+    /// POSIX `/bin/sh -c`
+    Sh,
+    /// Windows `cmd /C`
+    Cmd,
+    /// PowerShell `powershell -Command`
+    Powershell,
+    /// A custom interpreter program and the flag used to hand it a command string
+    Custom { program: String, arg: String },
+}
+
+impl Shell {
+    /// The program and command-flag to invoke for this shell, e.g. `("/bin/sh", "-c")`
+    pub fn program_and_arg(&self) -> (&str, &str) {
+        match self {
+            Shell::Sh => ("/bin/sh", "-c"),
+            Shell::Cmd => ("cmd", "/C"),
+            Shell::Powershell => ("powershell", "-Command"),
+            Shell::Custom { program, arg } => (program, arg),
+        }
+    }
+
+    /// Picks a [Shell] for `program`, recognizing well-known interpreters by
+    /// name and otherwise treating it as a custom `-c` style interpreter.
+    pub fn from_program(program: &str) -> Shell {
+        match program {
+            "cmd" | "cmd.exe" => Shell::Cmd,
+            "powershell" | "powershell.exe" | "pwsh" => Shell::Powershell,
+            "sh" | "/bin/sh" => Shell::Sh,
+            program => Shell::Custom {
+                program: program.to_string(),
+                arg: "-c".to_string(),
+            },
+        }
+    }
+
+    /// The default shell for the current platform, honoring a `KONK_SHELL`
+    /// or `SHELL` environment variable override (in that order).
+    pub fn detect() -> Shell {
+        if let Ok(program) = env::var("KONK_SHELL").or_else(|_| env::var("SHELL")) {
+            return Shell::from_program(&program);
+        }
+
+        if cfg!(windows) {
+            Shell::Cmd
+        } else {
+            Shell::Sh
+        }
+    }
+}
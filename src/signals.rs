@@ -0,0 +1,117 @@
+//! Shared two-stage SIGINT/SIGTERM handling, used by both a one-shot `run`
+//! and a long-lived `--watch` session to give supervised commands a chance
+//! to exit cleanly before escalating to SIGKILL.
+
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use nix::{sys::signal, unistd};
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    iterator::Signals,
+};
+
+/// A running command's kill target: its PID, and whether that PID identifies
+/// a process group (see [use_process_group](crate::runnable::Runnable::use_process_group))
+/// that a signal should be sent to as a whole.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ChildTarget {
+    pid: u32,
+    process_group: bool,
+}
+
+impl From<&crate::runnable::RunHandle> for ChildTarget {
+    fn from(handle: &crate::runnable::RunHandle) -> Self {
+        ChildTarget {
+            pid: handle.pid(),
+            process_group: handle.process_group(),
+        }
+    }
+}
+
+/// Installs handlers that, on the first SIGINT/SIGTERM, forward the signal
+/// to every target currently in `targets` and arm a `kill_timeout`-second
+/// escalation to SIGKILL; a second signal escalates immediately.
+///
+/// `targets` is read fresh each time a signal is forwarded or the timeout
+/// escalates, so callers can keep mutating it (e.g. clearing it between
+/// commands, or populating it as commands are spawned under `--watch`)
+/// after this returns.
+pub(crate) fn install_signal_handlers(
+    targets: Arc<Mutex<Vec<ChildTarget>>>,
+    kill_timeout: u16,
+) -> Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+
+    thread::spawn(move || {
+        let mut received_signal = false;
+
+        for raw_signal in signals.forever() {
+            if let SIGINT | SIGTERM = raw_signal {
+                let sig = if raw_signal == SIGINT {
+                    signal::SIGINT
+                } else {
+                    signal::SIGTERM
+                };
+
+                if received_signal {
+                    eprintln!("Received signal again. Killing processes.");
+                    kill_all_and_exit(&targets);
+                } else {
+                    received_signal = true;
+
+                    // Commands in their own process group (see
+                    // [Runnable::use_process_group]) no longer share our
+                    // terminal's foreground process group, so forward the
+                    // signal ourselves to give them a chance to shut down
+                    // cleanly before the timeout escalates to SIGKILL.
+                    kill_targets(&targets, sig);
+
+                    let targets = targets.clone();
+
+                    thread::spawn(move || {
+                        thread::sleep(Duration::from_secs(kill_timeout.into()));
+                        eprintln!("Timeout. Killing child processes.");
+                        kill_all_and_exit(&targets);
+                    });
+
+                    eprintln!("Received signal. Waiting for child processes to exit.");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+pub(crate) fn kill_targets(targets: &Arc<Mutex<Vec<ChildTarget>>>, sig: signal::Signal) {
+    for target in targets.lock().unwrap().iter() {
+        send_signal(target, sig);
+    }
+}
+
+fn kill_all_and_exit(targets: &Arc<Mutex<Vec<ChildTarget>>>) {
+    kill_targets(targets, signal::SIGKILL);
+    std::process::exit(130);
+}
+
+pub(crate) fn send_signal(target: &ChildTarget, sig: signal::Signal) {
+    // https://github.com/nix-rust/nix/issues/656#issuecomment-2056684715
+    let raw_pid = target.pid as i32;
+    let pid = unistd::Pid::from_raw(if target.process_group {
+        -raw_pid
+    } else {
+        raw_pid
+    });
+
+    eprintln!("Sending {sig} to process {}.", pid);
+
+    match signal::kill(pid, sig) {
+        Err(e) => eprintln!("Failed to send {sig} to process {}: {:?}", pid, e),
+        Ok(_) => {}
+    };
+}
@@ -0,0 +1,158 @@
+//! Supervisor for `run --watch`: re-runs commands whenever a watched path
+//! changes, coalescing bursts of filesystem events into a single restart.
+
+use std::{
+    path::Path,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use nix::sys::signal;
+use notify::{RecursiveMode, Watcher};
+
+use crate::signals::{install_signal_handlers, kill_targets, ChildTarget};
+
+/// Policy controlling what happens when a change event arrives while
+/// commands are still running.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum OnBusy {
+    /// Kill the currently running commands and start a fresh run immediately.
+    Restart,
+    /// Let the current run finish, then start exactly one more run.
+    Queue,
+    /// Drop the change event and keep the current run going.
+    Ignore,
+}
+
+/// Options controlling a watch session.
+pub struct WatchOpts {
+    /// Paths to watch for changes
+    pub paths: Vec<String>,
+
+    /// Window for coalescing bursts of change events into a single restart
+    pub debounce: Duration,
+
+    /// Policy for a change event arriving while commands are still running
+    pub on_busy: OnBusy,
+
+    /// Time (in seconds) to wait for processes to exit after a signal before killing them
+    pub kill_timeout: u16,
+}
+
+enum Event {
+    Changed,
+    Finished(Result<()>),
+}
+
+/// Watches `opts.paths` for changes, calling `run_once` to (re)run commands
+/// on startup and after every debounced change, forever (until a SIGINT or
+/// SIGTERM is received).
+///
+/// `run_once` is handed a shared, mutable set of live kill targets that it
+/// should populate as it spawns commands, so that a subsequent change event
+/// (or a signal) can kill the currently running processes.
+pub fn run<F>(opts: WatchOpts, run_once: F) -> Result<()>
+where
+    F: Fn(Arc<Mutex<Vec<ChildTarget>>>) -> Result<()> + Send + Sync + 'static,
+{
+    let WatchOpts {
+        paths,
+        debounce,
+        on_busy,
+        kill_timeout,
+    } = opts;
+
+    let (tx, rx) = mpsc::channel::<Event>();
+
+    let (raw_tx, raw_rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if res.is_ok() {
+            let _ = raw_tx.send(());
+        }
+    })
+    .context("create filesystem watcher")?;
+
+    for path in &paths {
+        watcher
+            .watch(Path::new(path), RecursiveMode::Recursive)
+            .with_context(|| format!("watch path: {}", path))?;
+    }
+
+    let debounce_tx = tx.clone();
+    thread::spawn(move || {
+        let mut pending = false;
+
+        loop {
+            match raw_rx.recv_timeout(debounce) {
+                Ok(()) => pending = true,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if pending {
+                        pending = false;
+
+                        if debounce_tx.send(Event::Changed).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    let run_once = Arc::new(run_once);
+    let live_pids: Arc<Mutex<Vec<ChildTarget>>> = Arc::new(Mutex::new(Vec::new()));
+
+    install_signal_handlers(live_pids.clone(), kill_timeout)?;
+
+    let spawn_run = {
+        let tx = tx.clone();
+        move |run_once: Arc<F>, live_pids: Arc<Mutex<Vec<ChildTarget>>>| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let result = run_once(live_pids);
+                let _ = tx.send(Event::Finished(result));
+            });
+        }
+    };
+
+    spawn_run(run_once.clone(), live_pids.clone());
+    let mut running = true;
+    let mut rerun_pending = false;
+
+    for event in rx {
+        match event {
+            Event::Changed if !running => {
+                live_pids.lock().unwrap().clear();
+                spawn_run(run_once.clone(), live_pids.clone());
+                running = true;
+            }
+            Event::Changed => match on_busy {
+                OnBusy::Restart => {
+                    kill_targets(&live_pids, signal::SIGKILL);
+                    rerun_pending = true;
+                }
+                OnBusy::Queue => rerun_pending = true,
+                OnBusy::Ignore => {}
+            },
+            Event::Finished(result) => {
+                if let Err(err) = result {
+                    eprintln!("watch: run failed: {err:?}");
+                }
+
+                running = false;
+
+                if rerun_pending {
+                    rerun_pending = false;
+                    live_pids.lock().unwrap().clear();
+                    spawn_run(run_once.clone(), live_pids.clone());
+                    running = true;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+